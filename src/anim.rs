@@ -0,0 +1,127 @@
+// Animated zoom output: renders a smooth zoom into a target point and writes
+// an animated GIF via the `gif` crate.
+
+use std::fs::File;
+use std::path::Path;
+
+use gif::{Encoder, Frame, Repeat};
+use num_traits::{Float, ToPrimitive};
+
+use crate::{gen_mandelbrot, color_for_val_with_config, AnimationConfig, Config, Point, Rect, Scalar, Size};
+
+/// GIF palettes are limited to 256 colors. We reserve the last slot for
+/// interior points (`count == limit`), which stay black as in the static
+/// renderer, and fill the rest from `color_for_val_with_config`.
+const MAX_PALETTE_COLORS: usize = 256;
+
+fn build_global_palette<T: Scalar>(config: &Config<T>) -> (Vec<u8>, usize) {
+    let steps = config.color_steps as usize;
+
+    if steps + 1 > MAX_PALETTE_COLORS {
+        panic!("Config Error: color_steps ({}) is too large for an indexed GIF palette", steps);
+    }
+
+    let mut palette = Vec::with_capacity(steps * 3);
+    for val in 0..steps {
+        let (r, g, b) = color_for_val_with_config(val as f32, config);
+        palette.push(r);
+        palette.push(g);
+        palette.push(b);
+    }
+
+    let black_index = steps;
+    palette.push(0);
+    palette.push(0);
+    palette.push(0);
+
+    (palette, black_index)
+}
+
+/// Finds the closest entry in the flat RGB `palette` to `target`. Used to
+/// index frames so smooth (fractional-iteration) colors land on the nearest
+/// available GIF palette entry instead of being truncated back to bands.
+fn nearest_palette_index(palette: &[u8], target: (u8, u8, u8)) -> u8 {
+    let mut best = 0;
+    let mut best_dist = u32::max_value();
+
+    for (i, chunk) in palette.chunks(3).enumerate() {
+        let dr = target.0 as i32 - chunk[0] as i32;
+        let dg = target.1 as i32 - chunk[1] as i32;
+        let db = target.2 as i32 - chunk[2] as i32;
+        let dist = (dr * dr + dg * dg + db * db) as u32;
+
+        if dist < best_dist {
+            best_dist = dist;
+            best = i;
+        }
+    }
+
+    best as u8
+}
+
+fn frame_window<T: Scalar>(config: &Config<T>, anim: &AnimationConfig<T>, n: u32) -> Rect<T> {
+    let scale = anim.zoom_factor.powi(n as i32);
+    let window = &config.window;
+
+    // Re-center geometrically toward the target so it stays fixed on screen
+    // as `window.size` shrinks.
+    let origin_x = anim.target.x - (anim.target.x - window.origin.x) * scale;
+    let origin_y = anim.target.y - (anim.target.y - window.origin.y) * scale;
+
+    Rect {
+        origin: Point { x: origin_x, y: origin_y },
+        size: Size {
+            width: window.size.width * scale,
+            height: window.size.height * scale,
+        },
+    }
+}
+
+pub fn render_animation<T: Scalar>(config: &Config<T>, config_file_path: &Path) {
+    let anim = config.animation.as_ref()
+        .expect("Config Error: --animate requires an `animation` block in the config");
+
+    let root = match config_file_path.file_stem() {
+        None => unreachable!(),
+        Some(r) => r.to_str().unwrap().to_string(),
+    };
+
+    let output_path_string = root + "-anim.gif";
+    let output_path = Path::new(&output_path_string);
+
+    let img_width = (config.ppu as f32 * config.window.size.width.to_f32().unwrap()) as u16;
+    let img_height = (config.ppu as f32 * config.window.size.height.to_f32().unwrap()) as u16;
+
+    println!("Generating animation at {} with size {}x{}, {} frames",
+             output_path.display(), img_width, img_height, anim.frames);
+
+    let (palette, black_index) = build_global_palette(config);
+
+    let mut output_file = File::create(output_path).unwrap();
+    let mut encoder = Encoder::new(&mut output_file, img_width, img_height, &palette).unwrap();
+    encoder.set_repeat(Repeat::Infinite).unwrap();
+
+    let size = Size { width: img_width as f32, height: img_height as f32 };
+
+    for n in 0..anim.frames {
+        let mut frame_config = config.clone();
+        frame_config.window = frame_window(config, anim, n);
+
+        let imgdata = gen_mandelbrot(&size, &frame_config);
+
+        let indices: Vec<u8> = imgdata.iter().map(|&val| {
+            if val >= config.limit as f32 {
+                black_index as u8
+            } else {
+                let rgb = color_for_val_with_config(val, &frame_config);
+                nearest_palette_index(&palette, rgb)
+            }
+        }).collect();
+
+        let mut frame = Frame::from_indexed_pixels(img_width, img_height, &indices, None);
+        frame.delay = anim.delay;
+        encoder.write_frame(&frame).unwrap();
+
+        println!("Wrote frame {}/{}", n + 1, anim.frames);
+    }
+}
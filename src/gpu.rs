@@ -0,0 +1,148 @@
+// GPU compute backend for `gen_mandelbrot`, built on wgpu. Uploads the
+// window/size/limit into a uniform buffer, dispatches a compute shader that
+// runs the same escape-time loop as the CPU path per pixel, then maps the
+// resulting storage buffer back into a plain `Vec<u32>` so the rest of the
+// pipeline (coloring, ppm writing) is unchanged.
+
+use wgpu::util::DeviceExt;
+
+use crate::{Config, Size};
+
+const SHADER: &str = include_str!("shader.wgsl");
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Params {
+    origin: [f32; 2],
+    win_size: [f32; 2],
+    img_size: [f32; 2],
+    limit: u32,
+    _pad: u32,
+}
+
+unsafe impl bytemuck::Pod for Params {}
+unsafe impl bytemuck::Zeroable for Params {}
+
+/// Runs `gen_mandelbrot` on the GPU. Returns `None` if no adapter is
+/// available so the caller can fall back to the threaded CPU path. Only
+/// `f32` coordinates are supported; `f64`-precision configs always fall
+/// back to the CPU path (see `Scalar::try_gpu` in main.rs).
+pub fn gen_mandelbrot_gpu(size: &Size<f32>, config: &Config<f32>) -> Option<Vec<u32>> {
+    let window = &config.window;
+    let data_size = size.width as u32 * size.height as u32;
+
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+
+    let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+        power_preference: wgpu::PowerPreference::HighPerformance,
+        compatible_surface: None,
+        force_fallback_adapter: false,
+    }))?;
+
+    let (device, queue) = pollster::block_on(adapter.request_device(
+        &wgpu::DeviceDescriptor {
+            label: Some("mandelbrot-device"),
+            features: wgpu::Features::empty(),
+            limits: wgpu::Limits::default(),
+        },
+        None,
+    ))
+    .ok()?;
+
+    let output_size = (data_size as usize * std::mem::size_of::<u32>()) as u64;
+
+    // Large `ppu` windows can ask for an output buffer bigger than the
+    // device's storage-buffer binding limit; fall back to the CPU path
+    // instead of letting wgpu panic on a validation error at submit time.
+    if output_size > device.limits().max_storage_buffer_binding_size as u64 {
+        return None;
+    }
+
+    let params = Params {
+        origin: [window.origin.x, window.origin.y],
+        win_size: [window.size.width, window.size.height],
+        img_size: [size.width, size.height],
+        limit: config.limit,
+        _pad: 0,
+    };
+
+    let params_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("mandelbrot-params"),
+        contents: bytemuck::bytes_of(&params),
+        usage: wgpu::BufferUsages::UNIFORM,
+    });
+
+    let output_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("mandelbrot-output"),
+        size: output_size,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    });
+
+    let staging_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("mandelbrot-staging"),
+        size: output_size,
+        usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("mandelbrot-shader"),
+        source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+    });
+
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: Some("mandelbrot-pipeline"),
+        layout: None,
+        module: &shader,
+        entry_point: "main",
+    });
+
+    let bind_group_layout = pipeline.get_bind_group_layout(0);
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("mandelbrot-bind-group"),
+        layout: &bind_group_layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: params_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: output_buffer.as_entire_binding(),
+            },
+        ],
+    });
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("mandelbrot-encoder"),
+    });
+
+    {
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("mandelbrot-pass"),
+            timestamp_writes: None,
+        });
+        pass.set_pipeline(&pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+
+        let workgroups_x = (size.width as u32 + 15) / 16;
+        let workgroups_y = (size.height as u32 + 15) / 16;
+        pass.dispatch_workgroups(workgroups_x, workgroups_y, 1);
+    }
+
+    encoder.copy_buffer_to_buffer(&output_buffer, 0, &staging_buffer, 0, output_size);
+    queue.submit(Some(encoder.finish()));
+
+    let slice = staging_buffer.slice(..);
+    let (tx, rx) = std::sync::mpsc::channel();
+    slice.map_async(wgpu::MapMode::Read, move |res| {
+        tx.send(res).unwrap();
+    });
+    device.poll(wgpu::Maintain::Wait);
+    rx.recv().unwrap().ok()?;
+
+    let data = bytemuck::cast_slice(&slice.get_mapped_range()).to_vec();
+
+    Some(data)
+}
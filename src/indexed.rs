@@ -0,0 +1,108 @@
+// Indexed-palette output: maps the full smooth gradient down to a small
+// fixed palette of K colors chosen to minimize total color error. Seeds
+// K-means with median-cut, then refines a few iterations.
+
+use std::fs::File;
+use std::path::Path;
+
+use gif::{Encoder, Frame, Repeat};
+use num_traits::ToPrimitive;
+
+use crate::palette_gen::median_cut;
+use crate::{color_for_val_with_config, gen_mandelbrot, Config, Scalar, Size};
+
+type Rgb = (u8, u8, u8);
+
+const KMEANS_ITERATIONS: usize = 4;
+
+fn nearest_center(centers: &[Rgb], p: Rgb) -> usize {
+    let mut best = 0;
+    let mut best_dist = u32::max_value();
+
+    for (i, c) in centers.iter().enumerate() {
+        let dr = p.0 as i32 - c.0 as i32;
+        let dg = p.1 as i32 - c.1 as i32;
+        let db = p.2 as i32 - c.2 as i32;
+        let dist = (dr * dr + dg * dg + db * db) as u32;
+
+        if dist < best_dist {
+            best_dist = dist;
+            best = i;
+        }
+    }
+
+    best
+}
+
+fn kmeans_refine(pixels: &[Rgb], mut centers: Vec<Rgb>, iterations: usize) -> Vec<Rgb> {
+    for _ in 0..iterations {
+        let mut sums = vec![(0u64, 0u64, 0u64, 0u64); centers.len()];
+
+        for &p in pixels {
+            let nearest = nearest_center(&centers, p);
+            let s = &mut sums[nearest];
+            s.0 += p.0 as u64;
+            s.1 += p.1 as u64;
+            s.2 += p.2 as u64;
+            s.3 += 1;
+        }
+
+        for (center, &(r_sum, g_sum, b_sum, count)) in centers.iter_mut().zip(sums.iter()) {
+            if count > 0 {
+                *center = ((r_sum / count) as u8, (g_sum / count) as u8, (b_sum / count) as u8);
+            }
+        }
+    }
+
+    centers
+}
+
+/// Renders the fractal once, quantizes its colors to `palette_size` entries,
+/// and writes a single-frame indexed GIF plus color table.
+pub fn render_indexed<T: Scalar>(config: &Config<T>, config_file_path: &Path, palette_size: usize) {
+    if palette_size == 0 || palette_size > 256 {
+        panic!("Config Error: palette_size ({}) must be between 1 and 256", palette_size);
+    }
+
+    let root = match config_file_path.file_stem() {
+        None => unreachable!(),
+        Some(r) => r.to_str().unwrap().to_string(),
+    };
+
+    let output_path_string = root + "-indexed.gif";
+    let output_path = Path::new(&output_path_string);
+
+    let img_width = (config.ppu as f32 * config.window.size.width.to_f32().unwrap()) as u16;
+    let img_height = (config.ppu as f32 * config.window.size.height.to_f32().unwrap()) as u16;
+
+    println!("Generating indexed image at {} with size {}x{}, {} colors",
+             output_path.display(), img_width, img_height, palette_size);
+
+    let size = Size { width: img_width as f32, height: img_height as f32 };
+    let imgdata = gen_mandelbrot(&size, config);
+
+    let colors: Vec<Rgb> = imgdata.iter()
+        .map(|&val| color_for_val_with_config(val, config))
+        .collect();
+
+    let seeds = median_cut(colors.clone(), palette_size);
+    let centers = kmeans_refine(&colors, seeds, KMEANS_ITERATIONS);
+
+    let mut flat_palette = Vec::with_capacity(centers.len() * 3);
+    for &(r, g, b) in &centers {
+        flat_palette.push(r);
+        flat_palette.push(g);
+        flat_palette.push(b);
+    }
+
+    let indices: Vec<u8> = colors.iter().map(|&c| nearest_center(&centers, c) as u8).collect();
+
+    let mut output_file = File::create(output_path).unwrap();
+    let mut encoder = Encoder::new(&mut output_file, img_width, img_height, &flat_palette).unwrap();
+    encoder.set_repeat(Repeat::Infinite).unwrap();
+
+    let frame = Frame::from_indexed_pixels(img_width, img_height, &indices, None);
+    encoder.write_frame(&frame).unwrap();
+
+    println!("Wrote indexed image with {} colors", centers.len());
+}
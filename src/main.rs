@@ -5,91 +5,158 @@ extern crate image;
 extern crate num_cpus;
 extern crate threadpool;
 extern crate rustc_serialize;
-
-use clap::{Arg, App};
+extern crate wgpu;
+extern crate pollster;
+extern crate bytemuck;
+extern crate gif;
+extern crate num_traits;
+
+mod gpu;
+mod anim;
+mod palette_gen;
+mod indexed;
+
+use clap::{Arg, App, ArgMatches};
 use time::PreciseTime;
 use rustc_serialize::json;
+use rustc_serialize::{Decodable, Encodable};
+use num_traits::{Float, NumCast, ToPrimitive, Zero};
 
+use std::fmt::Debug;
 use std::fs::File;
 use std::path::Path;
 use std::error::Error;
 use std::io::prelude::*;
 use std::thread::{self, JoinHandle};
 
+/// A coordinate scalar usable for the complex-plane math: either `f32`
+/// (the historical default) or `f64`, selected at runtime by the config's
+/// `precision` field. GPU compute only supports `f32` today, so the default
+/// `try_gpu` is a no-op and only the `f32` impl below overrides it.
+trait Scalar: Float + Decodable + Encodable + Clone + Debug + Send + 'static {
+    fn try_gpu(_size: &Size<f32>, _config: &Config<Self>) -> Option<Vec<u32>> where Self: Sized {
+        None
+    }
+}
+
+impl Scalar for f32 {
+    fn try_gpu(size: &Size<f32>, config: &Config<f32>) -> Option<Vec<u32>> {
+        gpu::gen_mandelbrot_gpu(size, config)
+    }
+}
+
+impl Scalar for f64 {}
+
 #[derive(Debug, RustcDecodable, RustcEncodable, Clone)]
-struct Size {
-    width: f32,
-    height: f32,
+struct Size<T> {
+    width: T,
+    height: T,
 }
 
 #[derive(Debug, RustcDecodable, RustcEncodable, Clone)]
-struct Point {
-    x: f32,
-    y: f32,
+struct Point<T> {
+    x: T,
+    y: T,
 }
 
 #[derive(Debug, RustcDecodable, RustcEncodable, Clone)]
-struct Rect {
-    origin: Point,
-    size: Size,
+struct Rect<T> {
+    origin: Point<T>,
+    size: Size<T>,
 }
 
-#[derive(Debug, RustcDecodable, RustcEncodable)]
-struct Config {
+#[derive(Debug, RustcDecodable, RustcEncodable, Clone)]
+struct AnimationConfig<T> {
+    target: Point<T>,
+    frames: u32,
+    zoom_factor: T,
+    delay: u16,
+}
+
+#[derive(Debug, RustcDecodable, RustcEncodable, Clone)]
+struct Config<T: Scalar> {
     ppu: u32,
     limit: u32,
     color_steps: f32,
     color_components: u8,
     color_palette: Vec<Vec<f32>>,
-    window: Rect,
+    window: Rect<T>,
+    animation: Option<AnimationConfig<T>>,
+    smooth: Option<bool>,
+    precision: Option<String>,
 }
 
-fn idx2point(idx: u32, width: u32) -> Point {
+/// Just enough of `Config` to read the `precision` field before we know
+/// which scalar type to decode the rest of the config as. Extra JSON keys
+/// are ignored by rustc_serialize, so this can decode the same file.
+#[derive(RustcDecodable)]
+struct PrecisionProbe {
+    precision: Option<String>,
+}
+
+fn idx2point(idx: u32, width: u32) -> Point<f32> {
     let x = idx % width;
     let y = idx / width;
     Point { x: x as f32, y: y as f32}
 }
 
-fn point2idx(p: Point, width: u32) -> u32 {
+fn point2idx(p: Point<f32>, width: u32) -> u32 {
     width * (p.y as u32) + (p.x as u32)
 }
 
-fn mandelbrot(cx: f32, cy: f32, limit: u32) -> u32 {
+/// Runs the escape-time loop and returns both the iteration count and the
+/// final squared modulus (`sum`) at bailout, which `smooth_iteration` needs
+/// to compute a fractional iteration count. Generic over the coordinate
+/// scalar so deep zooms can run at `f64` precision.
+fn mandelbrot<T: Scalar>(cx: T, cy: T, limit: u32) -> (u32, T) {
+    let four = T::from(4.0).unwrap();
+    let two = T::from(2.0).unwrap();
+
     let mut x = cx;
     let mut y = cy;
     let mut count = 0;
+    let mut sum = T::zero();
     while count < limit {
         let xy = x * y;
         let xx = x * x;
         let yy = y * y;
-        let sum = xx + yy;
-        if sum > 4.0 {
+        sum = xx + yy;
+        if sum > four {
             break
         }
         count += 1;
         x = xx - yy + cx;
-        y = xy * 2.0 + cy;
+        y = xy * two + cy;
     }
-    count as u32
+    (count as u32, sum)
 }
 
-fn gen_mandelbrot(size: &Size, config: &Config) -> Vec<u32> {
+/// Normalized iteration count: `nu = count + 1 - log2(log2(sqrt(sum)))`.
+/// Used in place of the integer count for smooth, band-free coloring.
+/// Coloring stays `f32` regardless of the coordinate precision used above.
+fn smooth_iteration<T: Scalar>(count: u32, sum: T) -> f32 {
+    let sum = sum.to_f32().unwrap();
+    count as f32 + 1.0 - sum.sqrt().log2().log2()
+}
+
+fn gen_mandelbrot<T: Scalar>(size: &Size<f32>, config: &Config<T>) -> Vec<f32> {
     let window = &config.window;
     let limit = config.limit;
+    let smooth = config.smooth.unwrap_or(false);
 
     let thread_count = (num_cpus::get() as f32 * 1.0).floor() as usize;
-    
+
     let data_size = size.width as u32 * size.height as u32;
-    let mut data: Vec<u32> = Vec::with_capacity(data_size as usize);
+    let mut data: Vec<f32> = Vec::with_capacity(data_size as usize);
 
-    let mut guards: Vec<JoinHandle<Vec<u32>>> = vec![];
+    let mut guards: Vec<JoinHandle<Vec<f32>>> = vec![];
 
     let thread_work = (data_size as f32 / thread_count as f32).ceil() as u32;
     let mut thread_start = 0;
     let mut thread_end = thread_start + thread_work;
 
     println!("Data Size: {}\nThread Work: {}", data_size, thread_work);
-    
+
     for t in 0..thread_count {
         let t_size = size.clone();
         let t_window = window.clone();
@@ -102,25 +169,31 @@ fn gen_mandelbrot(size: &Size, config: &Config) -> Vec<u32> {
         if t == thread_count - 1 {
             thread_end = data_size;
         }
-        
+
         let guard = thread::spawn(move || {
             println!("Starting thread [{}] working on data {} to {}", t, thread_start, thread_end);
-            
+
             let thread_size = thread_end - thread_start;
             let mut thread_data = Vec::with_capacity(thread_size as usize);
-            
-            for i in thread_start..thread_end { 
+
+            for i in thread_start..thread_end {
                 let p = idx2point(i, t_size.width as u32);
 
                 let px: f32 = p.x / t_size.width;
                 let py: f32 = p.y / t_size.height;
-        
-                let cx = t_window.origin.x + px * t_window.size.width;
-                let cy = (t_window.origin.y + t_window.size.height) - py * t_window.size.height;
-                
-                let c = mandelbrot(cx, cy, t_limit);
 
-                thread_data.push(c);
+                let cx = t_window.origin.x + T::from(px).unwrap() * t_window.size.width;
+                let cy = (t_window.origin.y + t_window.size.height) - T::from(py).unwrap() * t_window.size.height;
+
+                let (count, sum) = mandelbrot(cx, cy, t_limit);
+
+                let val = if smooth && count < t_limit {
+                    smooth_iteration(count, sum)
+                } else {
+                    count as f32
+                };
+
+                thread_data.push(val);
             }
 
             thread_data
@@ -144,19 +217,19 @@ fn rbg_from_palette(palette: &Vec<Vec<f32>>, idx: usize) -> (f32, f32, f32) {
     (color[0], color[1], color[2])
 }
 
-fn color_for_val_with_config(val: u32, config: &Config) -> (u8, u8, u8) {
+fn color_for_val_with_config<T: Scalar>(val: f32, config: &Config<T>) -> (u8, u8, u8) {
     let (r, g, b);
-    
+
     let limit = config.limit;
     let steps = config.color_steps;
     let palette = &config.color_palette;
-    
-    if val == limit as u32 {
+
+    if val >= limit as f32 {
         r = 0;
         g = 0;
         b = 0;
     } else {
-        let val = (val as f32 % steps) * (palette.len() as f32) / steps;
+        let val = (val % steps) * (palette.len() as f32) / steps;
         let left = val as usize % palette.len();
         let right = (left + 1) % palette.len();
 
@@ -170,7 +243,7 @@ fn color_for_val_with_config(val: u32, config: &Config) -> (u8, u8, u8) {
     (r, g, b)
 }
 
-fn validate_config(conf: &Config) {
+fn validate_config<T: Scalar>(conf: &Config<T>) {
 
     // Check if limit is 'to large'
     if conf.limit > 10000 {
@@ -191,52 +264,36 @@ fn validate_config(conf: &Config) {
     }
 }
 
-fn main() {
-    let start = PreciseTime::now();
-    
-    let args = App::new("Mandelbrot Generator")
-        .version(&crate_version!()[..])
-        .author("DJ Edmonson <djedmonson@gmail.com>")
-        .about("Generates a mandelbrot image")
-        .arg(Arg::with_name("CONFIG")
-             .long("config")
-             .help("Config JSON file to use. Output will be at <input_file_path>.png")
-             .required(true)
-             .takes_value(true))
-        .arg(Arg::with_name("output-palette")
-             .long("output-palette")
-             .help("Generate image with 100px squares of the provided colors in order. Outputs to <input_file_path>-palette.png"))
-        .get_matches();
-
-    let config_file_path = Path::new(args.value_of("CONFIG").unwrap());
-    println!("Getting config from {}", config_file_path.display());
-    
-    let mut config_file = match File::open(&config_file_path) {
-        Err(why) => panic!("Could not open config file at {}: {}",
-                           config_file_path.display(),
-                           Error::description(&why)),
-        Ok(f) => f,
-    };
-
-    let mut config_json = String::new();
-    match config_file.read_to_string(&mut config_json) {
-        Err(why) => panic!("Could not read config file at {}: {}",
-                           config_file_path.display(),
-                           Error::description(&why)),
-        Ok(_) => println!("Read config file"),
-    };
+/// Peeks at just the `precision` field of the config JSON so the caller can
+/// pick which scalar type to decode the rest of the config as. Defaults to
+/// `f32` (the historical behavior) if the field is absent or unrecognized.
+fn detect_precision(config_json: &str) -> &'static str {
+    match json::decode::<PrecisionProbe>(config_json) {
+        Ok(PrecisionProbe { precision: Some(ref p) }) if p.as_str() == "f64" => "f64",
+        _ => "f32",
+    }
+}
 
-    let config: Config = match json::decode(&config_json) {
-        Err(why) => panic!("Error parsing config JSON: {}",
-                           why),
+/// Decodes the config at the chosen scalar precision and runs the requested
+/// mode. Kept generic over `T` so the whole coordinate pipeline -
+/// `Point`/`Size`/`Rect`, `mandelbrot`, and the coordinate math in
+/// `gen_mandelbrot` - runs at that precision.
+fn run<'a, T: Scalar>(config_json: &str, config_file_path: &Path, args: &ArgMatches<'a>, start: PreciseTime) {
+    let config: Config<T> = match json::decode(config_json) {
+        Err(why) => panic!("Error parsing config JSON: {}", why),
         Ok(conf) => conf,
     };
 
     validate_config(&config);
 
     println!("Bootstrap time:\n{}", start.to(PreciseTime::now()));
-    
-    if args.is_present("output-palette") {
+
+    if args.is_present("animate") {
+        anim::render_animation(&config, config_file_path);
+    } else if args.is_present("indexed") {
+        let palette_size: usize = value_t!(args, "palette-size", usize).unwrap_or_else(|e| e.exit());
+        indexed::render_indexed(&config, config_file_path, palette_size);
+    } else if args.is_present("output-palette") {
         let root = match config_file_path.file_stem() {
             None => unreachable!(),
             Some(r) => r.to_str().unwrap().to_string(),
@@ -270,17 +327,32 @@ fn main() {
             Ok(f) => f,
         };
 
-        let img_width = config.ppu as f32 * config.window.size.width;
-        let img_height = config.ppu as f32 * config.window.size.height;
+        let img_width = config.ppu as f32 * config.window.size.width.to_f32().unwrap();
+        let img_height = config.ppu as f32 * config.window.size.height.to_f32().unwrap();
 
         println!("Generating image at {} with size {}x{}", output_path.display(), img_width, img_height);
-        
+
         let size = Size {width: img_width, height: img_height};
-        
+
         let render_start = PreciseTime::now();
         let mut phase_start = PreciseTime::now();
-        
-        let imgdata = gen_mandelbrot(&size, &config);
+
+        let imgdata: Vec<f32> = if args.value_of("backend") == Some("gpu") {
+            match T::try_gpu(&size, &config) {
+                Some(data) => {
+                    if config.smooth.unwrap_or(false) {
+                        println!("Warning: smooth coloring is not implemented on the GPU backend, using integer counts");
+                    }
+                    data.into_iter().map(|c| c as f32).collect()
+                }
+                None => {
+                    println!("GPU backend unavailable (no adapter or device, output too large for the device, or unsupported at this precision), falling back to CPU");
+                    gen_mandelbrot(&size, &config)
+                }
+            }
+        } else {
+            gen_mandelbrot(&size, &config)
+        };
 
         println!("Generation Duration:\n{}", phase_start.to(PreciseTime::now()));
 
@@ -289,13 +361,13 @@ fn main() {
         writeln!(output_file, "P6").unwrap();
         writeln!(output_file, "{} {}", img_width as usize, img_height as usize).unwrap();
         writeln!(output_file, "255").unwrap();
-        
+
         let mut linebuf = vec![0; img_width as usize * 3];
 
         for y in 0 .. img_height as usize {
             for x in 0 .. img_width as usize {
                 let idx = point2idx(Point{ x: x as f32, y: y as f32}, size.width as u32) as usize;
-        
+
                 let it = imgdata[idx];
 
                 let (r, g, b) = color_for_val_with_config(it, &config);
@@ -315,3 +387,79 @@ fn main() {
     println!("Total time:\n{}", start.to(PreciseTime::now()));
 }
 
+fn main() {
+    let start = PreciseTime::now();
+
+    let args = App::new("Mandelbrot Generator")
+        .version(&crate_version!()[..])
+        .author("DJ Edmonson <djedmonson@gmail.com>")
+        .about("Generates a mandelbrot image")
+        .arg(Arg::with_name("CONFIG")
+             .long("config")
+             .help("Config JSON file to use. Output will be at <input_file_path>.png")
+             .required_unless("from-image")
+             .takes_value(true))
+        .arg(Arg::with_name("from-image")
+             .long("from-image")
+             .help("Derive a color_palette from an image via median-cut quantization and print it as JSON, instead of rendering")
+             .takes_value(true))
+        .arg(Arg::with_name("palette-colors")
+             .long("palette-colors")
+             .help("Number of colors to derive with --from-image")
+             .takes_value(true)
+             .default_value("16"))
+        .arg(Arg::with_name("full-config")
+             .long("full-config")
+             .help("With --from-image, print a full Config skeleton instead of just the color_palette array"))
+        .arg(Arg::with_name("output-palette")
+             .long("output-palette")
+             .help("Generate image with 100px squares of the provided colors in order. Outputs to <input_file_path>-palette.png"))
+        .arg(Arg::with_name("backend")
+             .long("backend")
+             .help("Compute backend to use: gpu or cpu")
+             .takes_value(true)
+             .possible_values(&["gpu", "cpu"])
+             .default_value("cpu"))
+        .arg(Arg::with_name("animate")
+             .long("animate")
+             .help("Render a zoom animation using the config's `animation` block. Outputs to <input_file_path>-anim.gif"))
+        .arg(Arg::with_name("indexed")
+             .long("indexed")
+             .help("Render to an indexed-palette GIF with a constrained, optimized color table. Outputs to <input_file_path>-indexed.gif"))
+        .arg(Arg::with_name("palette-size")
+             .long("palette-size")
+             .help("Number of colors in the optimized palette with --indexed")
+             .takes_value(true)
+             .default_value("256"))
+        .get_matches();
+
+    if let Some(image_path) = args.value_of("from-image") {
+        let n_colors: usize = value_t!(args, "palette-colors", usize).unwrap_or_else(|e| e.exit());
+        palette_gen::run(image_path, n_colors, args.is_present("full-config"));
+        return;
+    }
+
+    let config_file_path = Path::new(args.value_of("CONFIG").unwrap());
+    println!("Getting config from {}", config_file_path.display());
+    
+    let mut config_file = match File::open(&config_file_path) {
+        Err(why) => panic!("Could not open config file at {}: {}",
+                           config_file_path.display(),
+                           Error::description(&why)),
+        Ok(f) => f,
+    };
+
+    let mut config_json = String::new();
+    match config_file.read_to_string(&mut config_json) {
+        Err(why) => panic!("Could not read config file at {}: {}",
+                           config_file_path.display(),
+                           Error::description(&why)),
+        Ok(_) => println!("Read config file"),
+    };
+
+    match detect_precision(&config_json) {
+        "f64" => run::<f64>(&config_json, config_file_path, &args, start),
+        _ => run::<f32>(&config_json, config_file_path, &args, start),
+    }
+}
+
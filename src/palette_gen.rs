@@ -0,0 +1,155 @@
+// Derives a `color_palette` from an input image via median-cut quantization,
+// so users can theme the fractal after a photo.
+
+use image::GenericImageView;
+use rustc_serialize::json;
+
+use std::path::Path;
+
+use crate::{Config, Point, Rect, Size};
+
+type Rgb = (u8, u8, u8);
+
+struct ColorBox {
+    pixels: Vec<Rgb>,
+}
+
+impl ColorBox {
+    fn channel(p: &Rgb, channel: usize) -> u8 {
+        match channel {
+            0 => p.0,
+            1 => p.1,
+            _ => p.2,
+        }
+    }
+
+    fn channel_range(&self, channel: usize) -> (u8, u8) {
+        let mut lo = 255u8;
+        let mut hi = 0u8;
+        for p in &self.pixels {
+            let v = ColorBox::channel(p, channel);
+            if v < lo { lo = v; }
+            if v > hi { hi = v; }
+        }
+        (lo, hi)
+    }
+
+    fn longest_channel(&self) -> (usize, u8) {
+        let mut longest = 0;
+        let mut longest_range = 0u8;
+        for channel in 0..3 {
+            let (lo, hi) = self.channel_range(channel);
+            let range = hi - lo;
+            if range >= longest_range {
+                longest = channel;
+                longest_range = range;
+            }
+        }
+        (longest, longest_range)
+    }
+
+    fn average(&self) -> Rgb {
+        let n = self.pixels.len() as u64;
+        let (r_sum, g_sum, b_sum) = self.pixels.iter().fold((0u64, 0u64, 0u64), |acc, p| {
+            (acc.0 + p.0 as u64, acc.1 + p.1 as u64, acc.2 + p.2 as u64)
+        });
+        ((r_sum / n) as u8, (g_sum / n) as u8, (b_sum / n) as u8)
+    }
+
+    fn split(mut self) -> (ColorBox, ColorBox) {
+        let (channel, _) = self.longest_channel();
+        self.pixels.sort_by_key(|p| ColorBox::channel(p, channel));
+
+        let mid = self.pixels.len() / 2;
+        let right = self.pixels.split_off(mid);
+        (ColorBox { pixels: self.pixels }, ColorBox { pixels: right })
+    }
+}
+
+pub(crate) fn median_cut(pixels: Vec<Rgb>, n_colors: usize) -> Vec<Rgb> {
+    let mut boxes = vec![ColorBox { pixels }];
+
+    while boxes.len() < n_colors {
+        let split_idx = boxes.iter()
+            .enumerate()
+            .filter(|(_, b)| b.pixels.len() >= 2)
+            .max_by_key(|(_, b)| b.longest_channel().1)
+            .map(|(idx, _)| idx);
+
+        let split_idx = match split_idx {
+            Some(idx) => idx,
+            None => break,
+        };
+
+        let target = boxes.remove(split_idx);
+        let (left, right) = target.split();
+        boxes.push(left);
+        boxes.push(right);
+    }
+
+    boxes.iter().map(ColorBox::average).collect()
+}
+
+fn hue(p: &Rgb) -> f32 {
+    let r = p.0 as f32 / 255.0;
+    let g = p.1 as f32 / 255.0;
+    let b = p.2 as f32 / 255.0;
+
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let delta = max - min;
+
+    if delta == 0.0 {
+        return 0.0;
+    }
+
+    let hue = if max == r {
+        60.0 * (((g - b) / delta).rem_euclid(6.0))
+    } else if max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    };
+
+    hue
+}
+
+fn skeleton_config(palette: Vec<Vec<f32>>) -> Config<f32> {
+    Config {
+        ppu: 500,
+        limit: 1000,
+        color_steps: palette.len() as f32,
+        color_components: 3,
+        color_palette: palette,
+        window: Rect {
+            origin: Point { x: -2.0, y: -1.2 },
+            size: Size { width: 3.0, height: 2.4 },
+        },
+        animation: None,
+        smooth: None,
+        precision: None,
+    }
+}
+
+/// Loads `image_path`, derives `n_colors` via median-cut, and prints either
+/// the bare `color_palette` JSON array or a full `Config` skeleton to stdout.
+pub fn run(image_path: &str, n_colors: usize, full_config: bool) {
+    let img = image::open(Path::new(image_path))
+        .unwrap_or_else(|why| panic!("Could not open image at {}: {}", image_path, why));
+
+    let pixels: Vec<Rgb> = img.pixels().map(|(_, _, p)| (p[0], p[1], p[2])).collect();
+
+    let mut colors = median_cut(pixels, n_colors);
+    colors.sort_by(|a, b| hue(a).partial_cmp(&hue(b)).unwrap());
+
+    let palette: Vec<Vec<f32>> = colors.iter()
+        .map(|&(r, g, b)| vec![r as f32, g as f32, b as f32])
+        .collect();
+
+    if full_config {
+        let config = skeleton_config(palette);
+        println!("{}", json::encode(&config).unwrap());
+    } else {
+        println!("{}", json::encode(&palette).unwrap());
+    }
+}